@@ -1,13 +1,18 @@
 use std::c_vec::CVec;
-use std::{io,mem,str};
+use std::{io,mem,ptr,str};
 use std::collections::HashMap;
-use libc::{c_void,c_long,size_t};
+use libc::{c_void,c_char,c_long,c_double,c_int,size_t};
 use super::{consts,err,info,opt};
 use super::err::ErrCode;
 use super::super::body::Body;
 use {header,Response};
 
 type CURL = c_void;
+type curl_off_t = i64;
+
+// Size of the buffer libcurl fills in with a human-readable error message
+// when CURLOPT_ERRORBUFFER is set. Mirrors libcurl's CURL_ERROR_SIZE.
+static CURL_ERROR_SIZE: uint = 256;
 
 #[link(name = "curl")]
 extern {
@@ -16,16 +21,55 @@ extern {
   pub fn curl_easy_perform(curl: *CURL) -> ErrCode;
   pub fn curl_easy_cleanup(curl: *CURL);
   pub fn curl_easy_getinfo(curl: *CURL, info: info::Key, ...) -> ErrCode;
+  pub fn curl_easy_strerror(code: ErrCode) -> *const c_char;
 }
 
 pub struct Easy {
-  curl: *CURL
+  curl: *CURL,
+  // Heap-allocated so the address registered with CURLOPT_ERRORBUFFER stays
+  // valid even when the `Easy` itself is moved (e.g. returned from `new`) --
+  // libcurl keeps the pointer we hand it, not a copy.
+  err_buf: Vec<u8>
+}
+
+// Callback invoked with each chunk of the response body as it arrives,
+// instead of buffering the whole response in memory. Returning fewer bytes
+// than were passed in tells libcurl to abort the transfer, which surfaces
+// as an Err from `perform_with_sink` (or `perform_full`).
+pub trait WriteHandler {
+  fn write(&mut self, data: &[u8]) -> uint;
+}
+
+// Callback invoked periodically during a transfer with the download/upload
+// byte counts libcurl has seen so far. Return `false` to abort the
+// transfer, which surfaces as an `Err` from `perform_full`.
+pub trait ProgressHandler {
+  fn progress(&mut self, dltotal: u64, dlnow: u64, ultotal: u64, ulnow: u64) -> bool;
+}
+
+struct ProgressCtx<'a> {
+  handler: Option<&'a mut ProgressHandler>
+}
+
+// The error `perform*` fails with: the raw libcurl code plus, when
+// available, the human-readable message `last_error` would return for it.
+pub struct PerformError {
+  pub code: err::ErrCode,
+  pub message: Option<String>
 }
 
 impl Easy {
   pub fn new() -> Easy {
+    let curl = unsafe { curl_easy_init() };
+    let mut err_buf = Vec::from_elem(CURL_ERROR_SIZE, 0u8);
+
+    unsafe {
+      curl_easy_setopt(curl, opt::ERRORBUFFER, err_buf.as_mut_ptr());
+    }
+
     Easy {
-      curl: unsafe { curl_easy_init() }
+      curl: curl,
+      err_buf: err_buf
     }
   }
 
@@ -44,8 +88,27 @@ impl Easy {
   }
 
   #[inline]
-  pub fn perform(&mut self, body: Option<&mut Body>) -> Result<Response, err::ErrCode> {
-    let mut builder = ResponseBuilder::new();
+  pub fn perform(&mut self, body: Option<&mut Body>) -> Result<Response, PerformError> {
+    self.perform_with_sink(body, None)
+  }
+
+  // Like `perform`, but streams response chunks into `sink` as they arrive
+  // instead of buffering them into `Response::body`. Leave `sink` as `None`
+  // to keep the default buffering behavior.
+  #[inline]
+  pub fn perform_with_sink(&mut self, body: Option<&mut Body>,
+                            sink: Option<&mut WriteHandler>) -> Result<Response, PerformError> {
+    self.perform_full(body, sink, None)
+  }
+
+  // Like `perform_with_sink`, but also reports transfer progress to
+  // `progress` as it happens. Returning `false` from the handler aborts the
+  // transfer, surfacing as an `Err`.
+  pub fn perform_full(&mut self, body: Option<&mut Body>, sink: Option<&mut WriteHandler>,
+                       progress: Option<&mut ProgressHandler>) -> Result<Response, PerformError> {
+    let mut builder = ResponseBuilder::new(sink);
+    let has_progress = progress.is_some();
+    let mut progress_ctx = ProgressCtx { handler: progress };
 
     unsafe {
       let resp_p: uint = mem::transmute(&builder);
@@ -63,25 +126,114 @@ impl Easy {
 
       curl_easy_setopt(self.curl, opt::HEADERFUNCTION, curl_header_fn);
       curl_easy_setopt(self.curl, opt::HEADERDATA, resp_p);
+
+      if has_progress {
+        let progress_p: uint = mem::transmute(&progress_ctx);
+
+        curl_easy_setopt(self.curl, opt::NOPROGRESS, 0i);
+
+        // Prefer the newer XFERINFOFUNCTION (libcurl >= 7.32.0); keep
+        // PROGRESSFUNCTION set too so older libcurl still reports progress.
+        curl_easy_setopt(self.curl, opt::XFERINFOFUNCTION, curl_xferinfo_fn);
+        curl_easy_setopt(self.curl, opt::XFERINFODATA, progress_p);
+
+        curl_easy_setopt(self.curl, opt::PROGRESSFUNCTION, curl_progress_fn);
+        curl_easy_setopt(self.curl, opt::PROGRESSDATA, progress_p);
+      } else {
+        // Reset from any previous call: otherwise a handle that was once
+        // given a progress handler keeps XFERINFODATA/PROGRESSDATA pointing
+        // at that call's (now dead) `ProgressCtx` on the stack.
+        curl_easy_setopt(self.curl, opt::NOPROGRESS, 1i);
+        curl_easy_setopt(self.curl, opt::XFERINFODATA, 0u);
+        curl_easy_setopt(self.curl, opt::PROGRESSDATA, 0u);
+      }
     }
 
     let err = unsafe { curl_easy_perform(self.curl) };
 
-    // If the request failed, abort here
+    // If the request failed, abort here with a descriptive message in tow.
     if !err.is_success() {
-      return Err(err);
+      return Err(PerformError { code: err, message: self.last_error(err) });
     }
 
     // Try to get the response code
-    builder.code = try!(self.get_response_code());
+    builder.code = match self.get_response_code() {
+      Ok(code) => code,
+      Err(e) => return Err(PerformError { code: e, message: self.last_error(e) })
+    };
 
-    Ok(builder.build())
+    let mut resp = builder.build();
+
+    // These are best-effort: libcurl may not always know them (e.g. a
+    // chunked response has no content length), so don't fail the whole
+    // transfer if they're unavailable.
+    //
+    // libcurl reports an unknown CONTENT_LENGTH_DOWNLOAD as -1; keep that as
+    // `None` rather than letting it cast into a bogus u64.
+    resp.set_content_length(self.get_info_double(info::CONTENT_LENGTH_DOWNLOAD).ok().and_then(|v| {
+      if v < 0f64 { None } else { Some(v as u64) }
+    }));
+    resp.set_total_time(self.get_info_double(info::TOTAL_TIME).unwrap_or(0f64));
+    resp.set_effective_url(match self.get_info_string(info::EFFECTIVE_URL).ok() {
+      Some(ref s) if s.is_empty() => None,
+      other => other
+    });
+
+    Ok(resp)
   }
 
   pub fn get_response_code(&self) -> Result<uint, err::ErrCode> {
     Ok(try!(self.get_info_long(info::RESPONSE_CODE)) as uint)
   }
 
+  pub fn get_info_string(&self, key: info::Key) -> Result<String, err::ErrCode> {
+    let v: *const c_char = ptr::null();
+    let res = unsafe { curl_easy_getinfo(self.curl, key, &v) };
+
+    if !res.is_success() {
+      return Err(res);
+    }
+
+    if v.is_null() {
+      return Ok(String::new());
+    }
+
+    Ok(unsafe { str::raw::from_c_str(v as *const i8).to_string() })
+  }
+
+  pub fn get_info_double(&self, key: info::Key) -> Result<f64, err::ErrCode> {
+    let v: c_double = 0f64;
+    let res = unsafe { curl_easy_getinfo(self.curl, key, &v) };
+
+    if !res.is_success() {
+      return Err(res);
+    }
+
+    Ok(v as f64)
+  }
+
+  // Returns a human-readable description of the most recent error on this
+  // handle, preferring the message libcurl wrote into the error buffer
+  // (which includes context like the offending URL) and falling back to
+  // `curl_easy_strerror(code)` when the buffer is empty.
+  pub fn last_error(&self, code: err::ErrCode) -> Option<String> {
+    let len = self.err_buf.iter().position(|&b| b == 0).unwrap_or(self.err_buf.len());
+
+    if len > 0 {
+      return str::from_utf8(self.err_buf.slice_to(len)).map(|s| s.to_string());
+    }
+
+    unsafe {
+      let msg = curl_easy_strerror(code);
+
+      if msg.is_null() {
+        None
+      } else {
+        Some(str::raw::from_c_str(msg as *const i8).to_string())
+      }
+    }
+  }
+
   fn get_info_long(&self, key: info::Key) -> Result<c_long, err::ErrCode> {
     let v: c_long = 0;
     let res = unsafe { curl_easy_getinfo(self.curl, key, &v) };
@@ -106,18 +258,24 @@ impl Drop for Easy {
  *
  */
 
-struct ResponseBuilder {
+struct ResponseBuilder<'a> {
   code: uint,
   hdrs: HashMap<String,Vec<String>>,
-  body: Vec<u8>
+  body: Vec<u8>,
+  sink: Option<&'a mut WriteHandler>,
+  http_version: Option<String>,
+  reason: Option<String>
 }
 
-impl ResponseBuilder {
-  fn new() -> ResponseBuilder {
+impl<'a> ResponseBuilder<'a> {
+  fn new(sink: Option<&'a mut WriteHandler>) -> ResponseBuilder<'a> {
     ResponseBuilder {
       code: 0,
       hdrs: HashMap::new(),
-      body: Vec::new()
+      body: Vec::new(),
+      sink: sink,
+      http_version: None,
+      reason: None
     }
   }
 
@@ -137,10 +295,51 @@ impl ResponseBuilder {
     }
   }
 
+  // A transfer that follows redirects (or gets a `100 Continue`) sees more
+  // than one status line; only the headers belonging to the final response
+  // should end up on the returned `Response`, so start over each time.
+  fn set_status_line(&mut self, version: String, reason: String) {
+    self.hdrs.clear();
+    self.code = 0;
+    self.http_version = Some(version);
+    self.reason = Some(reason);
+  }
+
   fn build(self) -> Response {
-    let ResponseBuilder { code, hdrs, body } = self;
-    Response::new(code, hdrs, body)
+    let ResponseBuilder { code, hdrs, body, http_version, reason, .. } = self;
+    let mut resp = Response::new(code, hdrs, body);
+    resp.set_http_version(http_version);
+    resp.set_reason(reason);
+    resp
+  }
+}
+
+// Parses an HTTP status line, e.g. "HTTP/1.1 200 OK", into its version and
+// reason phrase. Returns `None` if `line` isn't a status line at all.
+fn parse_status_line(line: &str) -> Option<(String, String)> {
+  if !line.starts_with("HTTP/") {
+    return None;
   }
+
+  let rest = line.slice_from(5);
+  let mut parts = rest.splitn(2, ' ');
+
+  let version = match parts.next() {
+    Some(v) => v,
+    None => return None
+  };
+
+  // What's left is "<code> <reason>"; drop the code to get the reason.
+  let reason = match parts.next() {
+    Some(code_and_reason) => {
+      let mut it = code_and_reason.splitn(2, ' ');
+      it.next();
+      it.next().unwrap_or("").trim()
+    }
+    None => ""
+  };
+
+  Some((version.to_string(), reason.to_string()))
 }
 
 /*
@@ -169,22 +368,42 @@ pub extern "C" fn curl_read_fn(p: *mut u8, size: size_t, nmemb: size_t, body: *m
 }
 
 #[no_mangle]
-pub extern "C" fn curl_write_fn(p: *mut u8, size: size_t, nmemb: size_t, resp: *mut ResponseBuilder) -> size_t {
-  if !resp.is_null() {
-    let builder: &mut ResponseBuilder = unsafe { mem::transmute(resp) };
-    let chunk = unsafe { CVec::new(p, (size * nmemb) as uint) };
-    builder.body.push_all(chunk.as_slice());
+pub extern "C" fn curl_write_fn(p: *mut u8, size: size_t, nmemb: size_t, resp: *mut ResponseBuilder<'static>) -> size_t {
+  if resp.is_null() {
+    return size * nmemb;
   }
 
-  size * nmemb
+  let builder: &mut ResponseBuilder = unsafe { mem::transmute(resp) };
+  let chunk = unsafe { CVec::new(p, (size * nmemb) as uint) };
+
+  match builder.sink {
+    Some(ref mut handler) => handler.write(chunk.as_slice()) as size_t,
+    None => {
+      builder.body.push_all(chunk.as_slice());
+      chunk.len() as size_t
+    }
+  }
 }
 
 #[no_mangle]
-pub extern "C" fn curl_header_fn(p: *mut u8, size: size_t, nmemb: size_t, resp: &mut ResponseBuilder) -> size_t {
-  // TODO: Skip the first call (it seems to be the status line)
-
+pub extern "C" fn curl_header_fn(p: *mut u8, size: size_t, nmemb: size_t, resp: &mut ResponseBuilder<'static>) -> size_t {
   let vec = unsafe { CVec::new(p, (size * nmemb) as uint) };
 
+  match str::from_utf8(vec.as_slice()) {
+    Some(line) => {
+      let line = line.trim_right();
+
+      match parse_status_line(line) {
+        Some((version, reason)) => {
+          resp.set_status_line(version, reason);
+          return vec.len() as size_t;
+        }
+        None => {}
+      }
+    }
+    None => {}
+  }
+
   match header::parse(vec.as_slice()) {
     Some((name, val)) => {
       resp.add_header(name, val);
@@ -193,4 +412,31 @@ pub extern "C" fn curl_header_fn(p: *mut u8, size: size_t, nmemb: size_t, resp:
   }
 
   vec.len() as size_t
+}
+
+fn run_progress(ctx: *mut ProgressCtx, dltotal: u64, dlnow: u64, ultotal: u64, ulnow: u64) -> c_int {
+  if ctx.is_null() {
+    return 0;
+  }
+
+  let ctx: &mut ProgressCtx = unsafe { mem::transmute(ctx) };
+
+  let keep_going = match ctx.handler {
+    Some(ref mut handler) => handler.progress(dltotal, dlnow, ultotal, ulnow),
+    None => true
+  };
+
+  if keep_going { 0 } else { 1 }
+}
+
+#[no_mangle]
+pub extern "C" fn curl_xferinfo_fn(ctx: *mut ProgressCtx, dltotal: curl_off_t, dlnow: curl_off_t,
+                                    ultotal: curl_off_t, ulnow: curl_off_t) -> c_int {
+  run_progress(ctx, dltotal as u64, dlnow as u64, ultotal as u64, ulnow as u64)
+}
+
+#[no_mangle]
+pub extern "C" fn curl_progress_fn(ctx: *mut ProgressCtx, dltotal: c_double, dlnow: c_double,
+                                    ultotal: c_double, ulnow: c_double) -> c_int {
+  run_progress(ctx, dltotal as u64, dlnow as u64, ultotal as u64, ulnow as u64)
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use ffi::easy::{Easy,PerformError};
+use ffi::err::ErrCode;
+use ffi::{list,opt};
+use ffi::list::List;
+
+// The missing ergonomic front-end over `Easy`: builds up a `Request` and
+// fires it off with `send()`, without the caller ever touching raw
+// `opt::Opt` constants or `ResponseBuilder` internals directly.
+
+pub enum Method {
+  Get,
+  Post,
+  Put,
+  Patch,
+  Delete
+}
+
+impl Method {
+  // The CUSTOMREQUEST string to use for methods libcurl doesn't special-case.
+  fn custom_name(&self) -> Option<&'static str> {
+    match *self {
+      Method::Get | Method::Post => None,
+      Method::Put => Some("PUT"),
+      Method::Patch => Some("PATCH"),
+      Method::Delete => Some("DELETE")
+    }
+  }
+}
+
+// `setopt` only ever fails with a bare `ErrCode` (libcurl didn't run a
+// transfer, so there's no error-buffer message to go with it).
+fn wrap_err(code: ErrCode) -> PerformError {
+  PerformError { code: code, message: None }
+}
+
+pub struct Request<'a> {
+  method: Method,
+  url: String,
+  headers: List,
+  body: Option<&'a [u8]>
+}
+
+impl<'a> Request<'a> {
+  pub fn new(method: Method, url: &str) -> Request<'a> {
+    Request {
+      method: method,
+      url: url.to_string(),
+      headers: list::List::new(),
+      body: None
+    }
+  }
+
+  pub fn header(mut self, name: &str, value: &str) -> Request<'a> {
+    self.headers.push(format!("{}: {}", name, value).as_slice());
+    self
+  }
+
+  pub fn body(mut self, body: &'a [u8]) -> Request<'a> {
+    self.body = Some(body);
+    self
+  }
+
+  // On failure, the returned `PerformError` carries libcurl's descriptive
+  // message alongside the code whenever the transfer actually ran.
+  pub fn send(self) -> Result<Response, PerformError> {
+    let mut easy = Easy::new();
+
+    try!(easy.setopt(opt::URL, self.url.as_slice()).map_err(wrap_err));
+
+    if !self.headers.is_empty() {
+      try!(easy.setopt(opt::HTTPHEADER, &self.headers).map_err(wrap_err));
+    }
+
+    match self.body {
+      Some(body) => {
+        // POSTFIELDS is only safe for a NUL-terminated C string unless
+        // POSTFIELDSIZE tells libcurl how many bytes to actually send.
+        try!(easy.setopt(opt::POSTFIELDS, body).map_err(wrap_err));
+        try!(easy.setopt(opt::POSTFIELDSIZE, body.len()).map_err(wrap_err));
+      }
+      None => {}
+    }
+
+    // Set the method explicitly rather than inferring GET vs. POST from
+    // whether a body happens to be present. This comes after POSTFIELDS,
+    // since setting POSTFIELDS switches libcurl's request method to POST.
+    match self.method {
+      Method::Get => { try!(easy.setopt(opt::HTTPGET, 1i).map_err(wrap_err)); }
+      Method::Post => { try!(easy.setopt(opt::POST, 1i).map_err(wrap_err)); }
+      _ => { try!(easy.setopt(opt::CUSTOMREQUEST, self.method.custom_name().unwrap()).map_err(wrap_err)); }
+    }
+
+    let resp = try!(easy.perform(None));
+
+    Ok(Response::from_raw(resp))
+  }
+}
+
+pub struct Response {
+  status: u32,
+  headers: HashMap<String,String>,
+  body: Vec<u8>
+}
+
+impl Response {
+  fn from_raw(resp: ::Response) -> Response {
+    let mut headers = HashMap::new();
+
+    for (name, vals) in resp.get_headers().iter() {
+      match vals.last() {
+        Some(val) => { headers.insert(name.clone(), val.clone()); }
+        None => {}
+      }
+    }
+
+    Response {
+      status: resp.get_code() as u32,
+      headers: headers,
+      body: resp.get_body().to_vec()
+    }
+  }
+
+  pub fn status(&self) -> u32 {
+    self.status
+  }
+
+  pub fn headers<'a>(&'a self) -> &'a HashMap<String,String> {
+    &self.headers
+  }
+
+  pub fn body<'a>(&'a self) -> &'a [u8] {
+    self.body.as_slice()
+  }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+pub struct Response {
+  code: uint,
+  hdrs: HashMap<String,Vec<String>>,
+  body: Vec<u8>,
+  content_length: Option<u64>,
+  total_time: f64,
+  effective_url: Option<String>,
+  http_version: Option<String>,
+  reason: Option<String>
+}
+
+impl Response {
+  pub fn new(code: uint, hdrs: HashMap<String,Vec<String>>, body: Vec<u8>) -> Response {
+    Response {
+      code: code,
+      hdrs: hdrs,
+      body: body,
+      content_length: None,
+      total_time: 0f64,
+      effective_url: None,
+      http_version: None,
+      reason: None
+    }
+  }
+
+  pub fn get_code(&self) -> uint {
+    self.code
+  }
+
+  pub fn get_headers<'a>(&'a self) -> &'a HashMap<String,Vec<String>> {
+    &self.hdrs
+  }
+
+  pub fn get_body<'a>(&'a self) -> &'a [u8] {
+    self.body.as_slice()
+  }
+
+  // Size of the response body as reported by CURLINFO_CONTENT_LENGTH_DOWNLOAD,
+  // when libcurl was able to determine it (e.g. from a Content-Length header).
+  pub fn get_content_length(&self) -> Option<u64> {
+    self.content_length
+  }
+
+  // Total time, in seconds, that the transfer took.
+  pub fn get_total_time(&self) -> f64 {
+    self.total_time
+  }
+
+  // The final URL the transfer ended up at, which may differ from the
+  // requested URL if the server issued any redirects.
+  pub fn get_effective_url<'a>(&'a self) -> Option<&'a str> {
+    self.effective_url.as_ref().map(|s| s.as_slice())
+  }
+
+  pub fn set_content_length(&mut self, len: Option<u64>) {
+    self.content_length = len;
+  }
+
+  pub fn set_total_time(&mut self, secs: f64) {
+    self.total_time = secs;
+  }
+
+  pub fn set_effective_url(&mut self, url: Option<String>) {
+    self.effective_url = url;
+  }
+
+  // The HTTP version of the final response, e.g. "1.1".
+  pub fn get_http_version<'a>(&'a self) -> Option<&'a str> {
+    self.http_version.as_ref().map(|s| s.as_slice())
+  }
+
+  // The reason phrase of the final response, e.g. "OK" or "Not Found".
+  pub fn get_reason<'a>(&'a self) -> Option<&'a str> {
+    self.reason.as_ref().map(|s| s.as_slice())
+  }
+
+  pub fn set_http_version(&mut self, version: Option<String>) {
+    self.http_version = version;
+  }
+
+  pub fn set_reason(&mut self, reason: Option<String>) {
+    self.reason = reason;
+  }
+}